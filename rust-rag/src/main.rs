@@ -1,13 +1,38 @@
 use anyhow::{Context, Result};
+use axum::extract::{Query as AxumQuery, State};
+use axum::routing::get;
+use axum::Router;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
 use clap::Parser;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
 use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
+use std::sync::Arc;
 
 #[derive(Parser, Debug)]
 #[command(name = "rust-rag-search")]
 #[command(about = "Fast FTS5 search for knowledge base", long_about = None)]
 struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Run a single search and print the results
+    Search(SearchArgs),
+    /// Start an HTTP server exposing the search API
+    Serve(ServeArgs),
+}
+
+#[derive(Parser, Debug, Clone)]
+struct SearchArgs {
     /// Path to SQLite database
     #[arg(short, long)]
     db_path: PathBuf,
@@ -31,9 +56,64 @@ struct Args {
     /// Search specific category only
     #[arg(short, long)]
     category: Option<String>,
+
+    /// Fall back to typo-tolerant fuzzy matching when MATCH returns too few hits
+    #[arg(long)]
+    fuzzy: bool,
+
+    /// Minimum blended bm25/fuzzy score for a fuzzy-only candidate to be kept
+    #[arg(long, default_value_t = 0.2)]
+    typo_tolerance: f64,
+
+    /// Text inserted before a matched term in the snippet (empty for jsonl)
+    #[arg(long, default_value = "")]
+    highlight_pre: String,
+
+    /// Text inserted after a matched term in the snippet (empty for jsonl)
+    #[arg(long, default_value = "")]
+    highlight_post: String,
+
+    /// Number of matching rows to skip before the first returned result
+    #[arg(long, default_value_t = 0)]
+    offset: usize,
+
+    /// Opaque cursor (from a previous response) to resume a scan after;
+    /// takes precedence over --offset
+    #[arg(long)]
+    after: Option<String>,
+
+    /// Include a category -> count breakdown for the query (Json output only)
+    #[arg(long)]
+    facets: bool,
+
+    /// Filter to a specific ISO 639-1 language code matching `docs.lang`
+    /// (e.g. "en", "fr"), or "auto" to detect the query's own language and
+    /// filter to it. "auto" is unreliable on short queries (whatlang needs
+    /// several sentences to classify confidently, but agent queries are
+    /// typically a handful of words) -- when detection doesn't find a
+    /// confident match, no language filter is applied and the json output
+    /// sets "language_auto_detect_failed" (jsonl and text output do not)
+    #[arg(long)]
+    language: Option<String>,
+}
+
+#[derive(Parser, Debug, Clone)]
+struct ServeArgs {
+    /// Path to SQLite database
+    #[arg(short, long)]
+    db_path: PathBuf,
+
+    /// Address to bind the HTTP server to
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    bind: String,
+
+    /// Number of pooled SQLite connections
+    #[arg(long, default_value_t = 8)]
+    pool_size: u32,
 }
 
-#[derive(Debug, Clone, clap::ValueEnum)]
+#[derive(Debug, Clone, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
 enum OutputFormat {
     Json,
     Jsonl,
@@ -46,165 +126,891 @@ struct SearchResult {
     category: String,
     title: String,
     snippet: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    matched_terms: Vec<MatchedTerm>,
     #[serde(skip_serializing_if = "Option::is_none")]
     rank: Option<f64>,
+    /// `docs.id`, exposed so an `--after` cursor can be built from
+    /// `(rank, rowid)` to resume a scan.
+    rowid: i64,
+    /// Detected language code for this document, if `docs.lang` is set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lang: Option<String>,
+}
+
+/// A term FTS5 matched inside a document's content, with character offsets
+/// into that document's original (unmarked) content.
+#[derive(Debug, Serialize, Deserialize)]
+struct MatchedTerm {
+    term: String,
+    start: usize,
+    end: usize,
+}
+
+/// Highlight delimiters used internally to recover match offsets from
+/// `highlight()` before the caller's own `--highlight-pre`/`--highlight-post`
+/// delimiters are applied via `snippet()`. Control characters so they can
+/// never collide with document content.
+const OFFSET_MARK_PRE: &str = "\u{1}";
+const OFFSET_MARK_POST: &str = "\u{2}";
+
+/// The `(rank, rowid)` pair a `--after` cursor resumes a scan from. Results
+/// stay stably ordered by `(rank, rowid)`, so this pair uniquely identifies
+/// "everything after the last seen row" while the ranks are unchanged.
+/// `bm25()` depends on corpus-wide statistics, so writes between pages can
+/// shift ranks and with them where the scan resumes.
+type Cursor = (f64, i64);
+
+/// A page of results plus the total match count, for offset/cursor paging.
+/// `has_more` accounts for which paging mode produced this page: offset
+/// paging compares `offset + results.len()` against `total`, while cursor
+/// paging (`--after`) instead counts rows strictly past the cursor, since an
+/// `--offset` of 0 would otherwise make every cursor page after the first
+/// look like it still has a successor whenever it holds fewer than `total`
+/// rows.
+struct SearchPage {
+    results: Vec<SearchResult>,
+    total: i64,
+    has_more: bool,
+}
+
+fn encode_cursor(rank: f64, rowid: i64) -> String {
+    BASE64.encode(format!("{rank}:{rowid}"))
 }
 
-struct Searcher {
-    conn: Connection,
+fn decode_cursor(cursor: &str) -> Result<Cursor> {
+    let raw = BASE64.decode(cursor).context("invalid --after cursor")?;
+    let raw = String::from_utf8(raw).context("invalid --after cursor encoding")?;
+    let (rank_str, rowid_str) = raw
+        .split_once(':')
+        .context("malformed --after cursor")?;
+    let rank: f64 = rank_str.parse().context("malformed rank in --after cursor")?;
+    let rowid: i64 = rowid_str.parse().context("malformed rowid in --after cursor")?;
+    Ok((rank, rowid))
 }
 
-impl Searcher {
-    fn new(db_path: &PathBuf) -> Result<Self> {
-        let conn = Connection::open(db_path)
-            .with_context(|| format!("Failed to open database: {:?}", db_path))?;
-        Ok(Self { conn })
+/// A single row fetched from the `docs`/`docs_fts` join, before it's turned
+/// into a ranked `SearchResult`.
+struct RawRow {
+    rowid: i64,
+    path: String,
+    category: String,
+    title: String,
+    content: String,
+    snippet: String,
+    marked_content: String,
+    rank: f64,
+    lang: Option<String>,
+}
+
+struct Searcher<'a> {
+    conn: &'a Connection,
+}
+
+/// Category/language restriction shared by most `Searcher` queries. Bundled
+/// into one struct because `category` and `language` are both `Option<&str>`
+/// and, as separate positional arguments, easy to transpose without the
+/// compiler noticing.
+#[derive(Clone, Copy, Default)]
+struct Filters<'a> {
+    category: Option<&'a str>,
+    language: Option<&'a str>,
+}
+
+/// Delimiters `highlight()`/`snippet()` wrap matched terms in.
+struct Highlight<'a> {
+    pre: &'a str,
+    post: &'a str,
+}
+
+/// `snippet()`/`highlight()` formatting shared by `run_match_query` and the
+/// fuzzy re-query it feeds.
+struct Rendering<'a> {
+    snippet_tokens: i64,
+    highlight: Highlight<'a>,
+}
+
+/// Tuning knobs for a single `search`/`search_category` call, bundled so the
+/// dispatch methods don't grow another positional parameter every time a new
+/// knob is added.
+struct SearchParams<'a> {
+    language: Option<&'a str>,
+    limit: usize,
+    offset: usize,
+    after: Option<Cursor>,
+    snippet_tokens: i64,
+    highlight: Highlight<'a>,
+    fuzzy: bool,
+    typo_tolerance: f64,
+}
+
+impl<'a> Searcher<'a> {
+    fn new(conn: &'a Connection) -> Self {
+        Self { conn }
     }
 
-    fn extract_snippet(&self, content: &str, query: &str, max_length: usize) -> String {
-        let content_lower = content.to_lowercase();
-        let query_lower = query.to_lowercase();
+    fn search(&self, query: &str, params: &SearchParams) -> Result<SearchPage> {
+        self.search_inner(query, None, params)
+    }
 
-        // Find first occurrence of query
-        if let Some(pos) = content_lower.find(&query_lower) {
-            let start = pos.saturating_sub(max_length / 3);
-            let end = (pos + query.len() + (2 * max_length / 3)).min(content.len());
+    fn search_category(
+        &self,
+        query: &str,
+        category: &str,
+        params: &SearchParams,
+    ) -> Result<SearchPage> {
+        self.search_inner(query, Some(category), params)
+    }
 
-            let mut snippet = content[start..end].trim().to_string();
+    fn search_inner(
+        &self,
+        query: &str,
+        category: Option<&str>,
+        params: &SearchParams,
+    ) -> Result<SearchPage> {
+        let filters = Filters { category, language: params.language };
 
-            // Add ellipsis if truncated
-            if start > 0 {
-                snippet = format!("...{}", snippet);
-            }
-            if end < content.len() {
-                snippet.push_str("...");
-            }
+        // An empty/whitespace query has no FTS5 tokens to MATCH against, so
+        // treat it as a "browse" request: list documents directly rather
+        // than ranking a match that doesn't exist.
+        if query.trim().is_empty() {
+            let total = self.count_all(filters)?;
+            let snippet_chars = (params.snippet_tokens.max(0) as usize) * 6;
+            let results = self.browse(
+                filters,
+                params.limit as i64,
+                params.offset as i64,
+                snippet_chars,
+            )?;
+            let has_more = (params.offset + results.len()) < total as usize;
+            return Ok(SearchPage { results, total, has_more });
+        }
 
-            snippet
-        } else {
-            // If exact match not found, return beginning
-            let end = max_length.min(content.len());
-            let mut snippet = content[..end].trim().to_string();
-            if content.len() > max_length {
-                snippet.push_str("...");
+        let offset = effective_offset(params.offset, params.after);
+
+        let total = self.count_matches(query, filters)?;
+
+        let rendering = Rendering {
+            snippet_tokens: params.snippet_tokens,
+            highlight: Highlight { pre: params.highlight.pre, post: params.highlight.post },
+        };
+
+        let rows = self.run_match_query(
+            query,
+            filters,
+            params.after,
+            params.limit as i64,
+            offset as i64,
+            &rendering,
+        )?;
+        let mut results: Vec<SearchResult> =
+            rows.into_iter().map(Self::raw_row_into_result).collect();
+
+        // Fuzzy enrichment only makes sense against the first, bm25-ordered
+        // page; cursor/offset paging always continues the strict scan.
+        if params.fuzzy && offset == 0 && params.after.is_none() && results.len() < params.limit {
+            results = self.fuzzy_fallback(
+                query,
+                filters,
+                params.limit,
+                &rendering,
+                params.typo_tolerance,
+                results,
+            )?;
+        }
+
+        let has_more = match params.after {
+            Some(cursor) => {
+                let remaining = self.count_after_cursor(query, filters, cursor)?;
+                remaining > results.len() as i64
             }
-            snippet
+            None => (offset + results.len()) < total as usize,
+        };
+
+        Ok(SearchPage { results, total, has_more })
+    }
+
+    fn count_matches(&self, query: &str, filters: Filters) -> Result<i64> {
+        let mut sql = String::from(
+            r#"
+            SELECT count(*)
+            FROM docs d
+            JOIN docs_fts ON d.id = docs_fts.rowid
+            WHERE docs_fts MATCH ?
+        "#,
+        );
+        if filters.category.is_some() {
+            sql.push_str(" AND d.category = ?");
+        }
+        if filters.language.is_some() {
+            sql.push_str(" AND d.lang = ?");
+        }
+
+        let category_val: &str = filters.category.unwrap_or_default();
+        let language_val: &str = filters.language.unwrap_or_default();
+        let mut params: Vec<&dyn rusqlite::ToSql> = vec![&query];
+        if filters.category.is_some() {
+            params.push(&category_val);
         }
+        if filters.language.is_some() {
+            params.push(&language_val);
+        }
+
+        let total = self
+            .conn
+            .query_row(&sql, rusqlite::params_from_iter(params), |row| row.get(0))?;
+        Ok(total)
+    }
+
+    /// Counts matches strictly after `cursor` in `(rank, rowid)` order, i.e.
+    /// the rows an `--after` cursor hasn't consumed yet. Used for `has_more`
+    /// once cursor paging has taken over from `--offset`, which would
+    /// otherwise always compare against a 0 offset.
+    fn count_after_cursor(&self, query: &str, filters: Filters, cursor: Cursor) -> Result<i64> {
+        let mut sql = String::from(
+            r#"
+            WITH matched AS (
+                SELECT bm25(docs_fts) as rank, d.id
+                FROM docs d
+                JOIN docs_fts ON d.id = docs_fts.rowid
+                WHERE docs_fts MATCH ?
+        "#,
+        );
+        if filters.category.is_some() {
+            sql.push_str(" AND d.category = ?");
+        }
+        if filters.language.is_some() {
+            sql.push_str(" AND d.lang = ?");
+        }
+        sql.push_str(
+            r#"
+            )
+            SELECT count(*) FROM matched WHERE rank > ? OR (rank = ? AND id > ?)
+        "#,
+        );
+
+        let category_val: &str = filters.category.unwrap_or_default();
+        let language_val: &str = filters.language.unwrap_or_default();
+        let (cursor_rank, cursor_rowid) = cursor;
+
+        let mut params: Vec<&dyn rusqlite::ToSql> = vec![&query];
+        if filters.category.is_some() {
+            params.push(&category_val);
+        }
+        if filters.language.is_some() {
+            params.push(&language_val);
+        }
+        params.push(&cursor_rank);
+        params.push(&cursor_rank);
+        params.push(&cursor_rowid);
+
+        let remaining = self
+            .conn
+            .query_row(&sql, rusqlite::params_from_iter(params), |row| row.get(0))?;
+        Ok(remaining)
+    }
+
+    /// Total documents available to a "browse" (empty-query) request.
+    fn count_all(&self, filters: Filters) -> Result<i64> {
+        let mut sql = String::from("SELECT count(*) FROM docs d WHERE 1 = 1");
+        if filters.category.is_some() {
+            sql.push_str(" AND d.category = ?");
+        }
+        if filters.language.is_some() {
+            sql.push_str(" AND d.lang = ?");
+        }
+
+        let category_val: &str = filters.category.unwrap_or_default();
+        let language_val: &str = filters.language.unwrap_or_default();
+        let mut params: Vec<&dyn rusqlite::ToSql> = Vec::new();
+        if filters.category.is_some() {
+            params.push(&category_val);
+        }
+        if filters.language.is_some() {
+            params.push(&language_val);
+        }
+
+        let total = self
+            .conn
+            .query_row(&sql, rusqlite::params_from_iter(params), |row| row.get(0))?;
+        Ok(total)
     }
 
-    fn search(&self, query: &str, limit: usize, max_snippet: usize) -> Result<Vec<SearchResult>> {
+    /// Lists documents without any FTS5 match, for browsing/sampling the
+    /// corpus when `--query` is empty. There's no relevance signal to rank
+    /// by, so results are ordered by title instead of bm25.
+    fn browse(
+        &self,
+        filters: Filters,
+        limit: i64,
+        offset: i64,
+        snippet_chars: usize,
+    ) -> Result<Vec<SearchResult>> {
+        let mut sql = String::from(
+            r#"
+            SELECT d.id, d.path, d.category, d.title, d.content, d.lang
+            FROM docs d
+            WHERE 1 = 1
+        "#,
+        );
+        if filters.category.is_some() {
+            sql.push_str(" AND d.category = ?");
+        }
+        if filters.language.is_some() {
+            sql.push_str(" AND d.lang = ?");
+        }
+        sql.push_str(" ORDER BY d.title LIMIT ? OFFSET ?");
+
+        let category_val: &str = filters.category.unwrap_or_default();
+        let language_val: &str = filters.language.unwrap_or_default();
+        let mut params: Vec<&dyn rusqlite::ToSql> = Vec::new();
+        if filters.category.is_some() {
+            params.push(&category_val);
+        }
+        if filters.language.is_some() {
+            params.push(&language_val);
+        }
+        params.push(&limit);
+        params.push(&offset);
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let results = stmt
+            .query_map(rusqlite::params_from_iter(params), |row| {
+                let content: String = row.get(4)?;
+                let snippet: String = content.chars().take(snippet_chars).collect();
+                Ok(SearchResult {
+                    path: row.get(1)?,
+                    category: row.get(2)?,
+                    title: row.get(3)?,
+                    snippet,
+                    matched_terms: Vec::new(),
+                    rank: None,
+                    rowid: row.get(0)?,
+                    lang: row.get(5)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(results)
+    }
+
+    /// Category distribution for `query`, always across all categories even
+    /// when the caller is also filtering to one, so the result can drive a
+    /// follow-up `--category` choice.
+    fn facet_counts(&self, query: &str) -> Result<HashMap<String, i64>> {
         let sql = r#"
-            SELECT 
-                d.path,
-                d.category,
-                d.title,
-                d.content,
-                bm25(docs_fts) as rank
+            SELECT d.category, count(*)
             FROM docs d
             JOIN docs_fts ON d.id = docs_fts.rowid
             WHERE docs_fts MATCH ?
-            ORDER BY rank
-            LIMIT ?
+            GROUP BY d.category
         "#;
+        let mut stmt = self.conn.prepare(sql)?;
+        let facets = stmt
+            .query_map(rusqlite::params![query], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+            })?
+            .collect::<Result<HashMap<_, _>, _>>()?;
+        Ok(facets)
+    }
 
+    /// Category distribution across the whole corpus, for `--facets` in
+    /// browse mode where there's no query to `MATCH` against (FTS5 errors on
+    /// an empty MATCH expression).
+    fn facet_counts_all(&self) -> Result<HashMap<String, i64>> {
+        let sql = "SELECT category, count(*) FROM docs GROUP BY category";
         let mut stmt = self.conn.prepare(sql)?;
-        let results = stmt
-            .query_map([query, &limit.to_string()], |row| {
-                Ok((
-                    row.get::<_, String>(0)?,  // path
-                    row.get::<_, String>(1)?,  // category
-                    row.get::<_, String>(2)?,  // title
-                    row.get::<_, String>(3)?,  // content
-                    row.get::<_, f64>(4)?,     // rank
-                ))
+        let facets = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
             })?
+            .collect::<Result<HashMap<_, _>, _>>()?;
+        Ok(facets)
+    }
+
+    /// Dispatches to `facet_counts`/`facet_counts_all` depending on whether
+    /// `query` is blank, so `--facets` works in both FTS and browse mode.
+    fn resolve_facets(&self, query: &str) -> Result<HashMap<String, i64>> {
+        if query.trim().is_empty() {
+            self.facet_counts_all()
+        } else {
+            self.facet_counts(query)
+        }
+    }
+
+    /// Builds and runs the core FTS5 MATCH query. `category`, `after`
+    /// (keyset cursor) and `offset` (plain paging) each extend the base SQL
+    /// conditionally so this stays a single query path as filters grow,
+    /// rather than one duplicated block per combination.
+    fn run_match_query(
+        &self,
+        query: &str,
+        filters: Filters,
+        after: Option<Cursor>,
+        limit: i64,
+        offset: i64,
+        rendering: &Rendering,
+    ) -> Result<Vec<RawRow>> {
+        let (snippet_tokens, highlight) = (rendering.snippet_tokens, &rendering.highlight);
+        let (category, language) = (filters.category, filters.language);
+        let mut sql = String::from(
+            r#"
+            WITH matched AS (
+                SELECT
+                    d.id,
+                    d.path,
+                    d.category,
+                    d.title,
+                    d.content,
+                    snippet(docs_fts, 3, ?, ?, '…', ?) as snippet,
+                    highlight(docs_fts, 3, ?, ?) as marked_content,
+                    bm25(docs_fts) as rank,
+                    d.lang
+                FROM docs d
+                JOIN docs_fts ON d.id = docs_fts.rowid
+                WHERE docs_fts MATCH ?
+            "#,
+        );
+        if category.is_some() {
+            sql.push_str(" AND d.category = ?");
+        }
+        if language.is_some() {
+            sql.push_str(" AND d.lang = ?");
+        }
+        sql.push_str(
+            r#"
+            )
+            SELECT id, path, category, title, content, snippet, marked_content, rank, lang
+            FROM matched
+            "#,
+        );
+        if after.is_some() {
+            sql.push_str(" WHERE rank > ? OR (rank = ? AND id > ?)");
+        }
+        sql.push_str(" ORDER BY rank, id LIMIT ?");
+        if offset > 0 {
+            sql.push_str(" OFFSET ?");
+        }
+
+        let category_val: &str = category.unwrap_or_default();
+        let language_val: &str = language.unwrap_or_default();
+        let (cursor_rank, cursor_rowid) = after.unwrap_or((0.0, 0));
+
+        let mut params: Vec<&dyn rusqlite::ToSql> = vec![
+            &highlight.pre,
+            &highlight.post,
+            &snippet_tokens,
+            &OFFSET_MARK_PRE,
+            &OFFSET_MARK_POST,
+            &query,
+        ];
+        if category.is_some() {
+            params.push(&category_val);
+        }
+        if language.is_some() {
+            params.push(&language_val);
+        }
+        if after.is_some() {
+            params.push(&cursor_rank);
+            params.push(&cursor_rank);
+            params.push(&cursor_rowid);
+        }
+        params.push(&limit);
+        if offset > 0 {
+            params.push(&offset);
+        }
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt
+            .query_map(rusqlite::params_from_iter(params), Self::row_to_raw)?
             .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
 
-        Ok(results
-            .into_iter()
-            .map(|(path, category, title, content, rank)| {
-                let snippet = self.extract_snippet(&content, query, max_snippet);
-                SearchResult {
-                    path,
-                    category,
-                    title,
-                    snippet,
-                    rank: Some(rank),
+    fn row_to_raw(row: &rusqlite::Row) -> rusqlite::Result<RawRow> {
+        Ok(RawRow {
+            rowid: row.get(0)?,
+            path: row.get(1)?,
+            category: row.get(2)?,
+            title: row.get(3)?,
+            content: row.get(4)?,
+            snippet: row.get(5)?,
+            marked_content: row.get(6)?,
+            rank: row.get(7)?,
+            lang: row.get(8)?,
+        })
+    }
+
+    fn raw_row_into_result(row: RawRow) -> SearchResult {
+        SearchResult {
+            path: row.path,
+            category: row.category,
+            title: row.title,
+            snippet: row.snippet,
+            matched_terms: Self::extract_matched_terms(&row.marked_content),
+            rank: Some(row.rank),
+            rowid: row.rowid,
+            lang: row.lang,
+        }
+    }
+
+    /// Recovers matched terms and their character offsets in the original
+    /// content from a `highlight()` result wrapped with `OFFSET_MARK_PRE`/
+    /// `OFFSET_MARK_POST`. `highlight()` only inserts markers around matches,
+    /// it never truncates or otherwise rewrites the content, so offsets
+    /// computed here line up with the document's real content.
+    fn extract_matched_terms(marked_content: &str) -> Vec<MatchedTerm> {
+        let mut terms = Vec::new();
+        let mut in_match = false;
+        let mut current = String::new();
+        let mut start = 0usize;
+        let mut pos = 0usize;
+
+        for ch in marked_content.chars() {
+            if ch == '\u{1}' {
+                in_match = true;
+                start = pos;
+                current.clear();
+            } else if ch == '\u{2}' {
+                in_match = false;
+                terms.push(MatchedTerm {
+                    term: current.clone(),
+                    start,
+                    end: pos,
+                });
+            } else {
+                if in_match {
+                    current.push(ch);
                 }
-            })
-            .collect())
+                pos += 1;
+            }
+        }
+
+        terms
     }
 
-    fn search_category(
+    /// Second-pass fuzzy fallback used when the strict FTS5 MATCH above returns
+    /// fewer than `limit` rows. Widens the candidate set with per-token
+    /// prefix/OR FTS5 queries, then re-ranks the merged set with a blend of
+    /// the normalized bm25 rank, a Smith-Waterman-style skim score, and a
+    /// bounded-edit-distance token bonus so near-miss typos still surface.
+    ///
+    /// A prefix query alone only catches typos that are truncations at the
+    /// tail -- "paralell" isn't a prefix of "parallel" even though it's a
+    /// transposition 2 edits away. When the prefix query doesn't fill the
+    /// candidate pool, it's widened with a bounded, unranked scan of the
+    /// whole (filtered) corpus so the edit-distance/skim scoring below gets
+    /// a chance to catch those too.
+    fn fuzzy_fallback(
         &self,
         query: &str,
-        category: &str,
+        filters: Filters,
         limit: usize,
-        max_snippet: usize,
+        rendering: &Rendering,
+        typo_tolerance: f64,
+        mut results: Vec<SearchResult>,
     ) -> Result<Vec<SearchResult>> {
-        let sql = r#"
-            SELECT 
-                d.path,
-                d.category,
-                d.title,
-                d.content,
-                bm25(docs_fts) as rank
+        let seen: HashSet<String> = results.iter().map(|r| r.path.clone()).collect();
+        let candidate_limit = (limit * 5).max(limit) as i64;
+
+        let fts_query = Self::build_fuzzy_fts_query(query);
+        let mut rows = if fts_query.is_empty() {
+            Vec::new()
+        } else {
+            self.run_match_query(&fts_query, filters, None, candidate_limit, 0, rendering)?
+        };
+
+        if (rows.len() as i64) < candidate_limit {
+            let have: HashSet<i64> = rows.iter().map(|r| r.rowid).collect();
+            let broad = self.fuzzy_candidate_pool(filters, candidate_limit, rendering)?;
+            rows.extend(broad.into_iter().filter(|r| !have.contains(&r.rowid)));
+        }
+
+        let matcher = SkimMatcherV2::default();
+        let query_tokens: Vec<String> =
+            query.split_whitespace().map(|t| t.to_lowercase()).collect();
+
+        let mut scored: Vec<(f64, SearchResult)> = Vec::new();
+        for row in rows {
+            if seen.contains(&row.path) {
+                continue;
+            }
+            let haystack = format!("{} {}", row.title, row.content);
+            let fuzzy_score = matcher.fuzzy_match(&haystack, query).unwrap_or(0) as f64;
+            let norm_fuzzy = (fuzzy_score / 100.0).clamp(0.0, 1.0);
+            let norm_bm25 = 1.0 / (1.0 + row.rank.abs());
+            let edit_bonus = Self::token_edit_distance_bonus(&query_tokens, &haystack);
+            let combined = 0.5 * norm_bm25 + 0.4 * norm_fuzzy + 0.1 * edit_bonus;
+
+            if combined < typo_tolerance {
+                continue;
+            }
+
+            let mut result = Self::raw_row_into_result(row);
+            result.rank = Some(combined);
+            scored.push((combined, result));
+        }
+
+        scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+        results.extend(scored.into_iter().map(|(_, r)| r));
+        results.truncate(limit);
+        Ok(results)
+    }
+
+    fn build_fuzzy_fts_query(query: &str) -> String {
+        query
+            .split_whitespace()
+            .filter_map(|token| {
+                let sanitized: String = token.chars().filter(|c| c.is_alphanumeric()).collect();
+                if sanitized.is_empty() {
+                    None
+                } else {
+                    Some(format!("{}*", sanitized))
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" OR ")
+    }
+
+    /// An unranked, bounded scan of the (filtered) corpus used to widen the
+    /// fuzzy candidate pool beyond what a prefix FTS5 query can find. Rows
+    /// carry a sentinel `rank` far from any real `bm25()` value so they
+    /// don't get an unearned boost from `fuzzy_fallback`'s bm25 term --
+    /// whether they're kept depends on the fuzzy/edit-distance score alone.
+    fn fuzzy_candidate_pool(
+        &self,
+        filters: Filters,
+        limit: i64,
+        rendering: &Rendering,
+    ) -> Result<Vec<RawRow>> {
+        const UNRANKED_SENTINEL: f64 = 1_000.0;
+
+        let mut sql = String::from(
+            r#"
+            SELECT d.id, d.path, d.category, d.title, d.content, d.lang
             FROM docs d
-            JOIN docs_fts ON d.id = docs_fts.rowid
-            WHERE docs_fts MATCH ? AND d.category = ?
-            ORDER BY rank
-            LIMIT ?
-        "#;
+            WHERE 1 = 1
+        "#,
+        );
+        if filters.category.is_some() {
+            sql.push_str(" AND d.category = ?");
+        }
+        if filters.language.is_some() {
+            sql.push_str(" AND d.lang = ?");
+        }
+        sql.push_str(" LIMIT ?");
 
-        let mut stmt = self.conn.prepare(sql)?;
-        let results = stmt
-            .query_map([query, category, &limit.to_string()], |row| {
-                Ok((
-                    row.get::<_, String>(0)?,  // path
-                    row.get::<_, String>(1)?,  // category
-                    row.get::<_, String>(2)?,  // title
-                    row.get::<_, String>(3)?,  // content
-                    row.get::<_, f64>(4)?,     // rank
-                ))
+        let category_val: &str = filters.category.unwrap_or_default();
+        let language_val: &str = filters.language.unwrap_or_default();
+        let mut params: Vec<&dyn rusqlite::ToSql> = Vec::new();
+        if filters.category.is_some() {
+            params.push(&category_val);
+        }
+        if filters.language.is_some() {
+            params.push(&language_val);
+        }
+        params.push(&limit);
+
+        let snippet_chars = (rendering.snippet_tokens.max(0) as usize) * 6;
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt
+            .query_map(rusqlite::params_from_iter(params), |row| {
+                let content: String = row.get(4)?;
+                let snippet: String = content.chars().take(snippet_chars).collect();
+                Ok(RawRow {
+                    rowid: row.get(0)?,
+                    path: row.get(1)?,
+                    category: row.get(2)?,
+                    title: row.get(3)?,
+                    content: content.clone(),
+                    snippet,
+                    marked_content: content,
+                    rank: UNRANKED_SENTINEL,
+                    lang: row.get(5)?,
+                })
             })?
             .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
 
-        Ok(results
-            .into_iter()
-            .map(|(path, category, title, content, rank)| {
-                let snippet = self.extract_snippet(&content, query, max_snippet);
-                SearchResult {
-                    path,
-                    category,
-                    title,
-                    snippet,
-                    rank: Some(rank),
-                }
+    fn token_edit_distance_bonus(query_tokens: &[String], haystack: &str) -> f64 {
+        if query_tokens.is_empty() {
+            return 0.0;
+        }
+        let doc_tokens: Vec<String> = haystack.split_whitespace().map(|t| t.to_lowercase()).collect();
+        let hits = query_tokens
+            .iter()
+            .filter(|qt| {
+                let bound = if qt.chars().count() <= 5 { 1 } else { 2 };
+                doc_tokens.iter().any(|dt| strsim::levenshtein(qt, dt) <= bound)
             })
-            .collect())
+            .count();
+        hits as f64 / query_tokens.len() as f64
     }
 }
 
-fn format_results(results: &[SearchResult], query: &str, format: &OutputFormat) -> Result<String> {
+/// FTS5's `snippet()` windows by token count, not character count, so
+/// `--max-snippet` (a character budget) is converted with a rough
+/// characters-per-token estimate and clamped to 5-64 tokens (`snippet()`
+/// itself accepts at most 64).
+fn snippet_token_count(max_snippet: usize) -> i64 {
+    ((max_snippet / 6).clamp(5, 64)) as i64
+}
+
+/// Upper bounds for paging parameters before they reach a query builder.
+/// `limit`/`offset` get cast to `i64` in `search_inner`/`fuzzy_fallback`;
+/// an unclamped `usize` near `usize::MAX` wraps negative in that cast, and
+/// SQLite treats a negative `LIMIT` as "no limit" -- clamp first, especially
+/// since `limit`/`offset` on the `serve` endpoint come straight from
+/// untrusted HTTP query params.
+const MAX_LIMIT: usize = 1000;
+const MAX_OFFSET: usize = 1_000_000;
+
+fn clamp_limit(limit: usize) -> usize {
+    limit.clamp(1, MAX_LIMIT)
+}
+
+fn clamp_offset(offset: usize) -> usize {
+    offset.min(MAX_OFFSET)
+}
+
+/// `--after` resumes a cursor-based scan and takes precedence over
+/// `--offset`, which only applies to the first (cursor-less) page.
+fn effective_offset(offset: usize, after: Option<Cursor>) -> usize {
+    if after.is_some() {
+        0
+    } else {
+        offset
+    }
+}
+
+/// Maps a whatlang `Lang` (ISO 639-3) to the ISO 639-1 two-letter code that
+/// `docs.lang` and an explicit `--language` filter use. whatlang's own
+/// `Lang::code()` returns ISO 639-3 ("fra"), which doesn't match a corpus
+/// populated with the conventional two-letter codes ("fr") -- every variant
+/// whatlang supports has a stable two-letter equivalent, so each is mapped
+/// explicitly rather than falling back to the 639-3 code and risking that
+/// same silent mismatch.
+fn iso639_1(lang: whatlang::Lang) -> &'static str {
+    use whatlang::Lang::*;
+    match lang {
+        Epo => "eo", Eng => "en", Rus => "ru", Cmn => "zh", Spa => "es",
+        Por => "pt", Ita => "it", Ben => "bn", Fra => "fr", Deu => "de",
+        Ukr => "uk", Kat => "ka", Ara => "ar", Hin => "hi", Jpn => "ja",
+        Heb => "he", Yid => "yi", Pol => "pl", Amh => "am", Jav => "jv",
+        Kor => "ko", Nob => "nb", Dan => "da", Swe => "sv", Fin => "fi",
+        Tur => "tr", Nld => "nl", Hun => "hu", Ces => "cs", Ell => "el",
+        Bul => "bg", Bel => "be", Mar => "mr", Kan => "kn", Ron => "ro",
+        Slv => "sl", Hrv => "hr", Srp => "sr", Mkd => "mk", Lit => "lt",
+        Lav => "lv", Est => "et", Tam => "ta", Vie => "vi", Urd => "ur",
+        Tha => "th", Guj => "gu", Uzb => "uz", Pan => "pa", Aze => "az",
+        Ind => "id", Tel => "te", Pes => "fa", Mal => "ml", Ori => "or",
+        Mya => "my", Nep => "ne", Sin => "si", Khm => "km", Tuk => "tk",
+        Aka => "ak", Zul => "zu", Sna => "sn", Afr => "af", Lat => "la",
+        Slk => "sk", Cat => "ca", Tgl => "tl", Hye => "hy",
+    }
+}
+
+/// Detects the dominant language of `text` with a trigram-based detector,
+/// returning an ISO 639-1 code, or `None` when `text` is too short or
+/// ambiguous to classify confidently.
+fn detect_language(text: &str) -> Option<String> {
+    whatlang::detect(text)
+        .filter(|info| info.is_reliable())
+        .map(|info| iso639_1(info.lang()).to_string())
+}
+
+/// Outcome of resolving `--language`. Kept distinct from a plain
+/// `Option<String>` so callers can tell "filtering wasn't requested" apart
+/// from "`auto` was requested but detection didn't find a confident match" --
+/// whatlang needs several sentences to classify reliably, and typical agent
+/// search queries are a handful of words, so `auto` silently matching
+/// nothing is the common case rather than the exception.
+#[derive(Clone)]
+enum LanguageResolution {
+    NotRequested,
+    Detected(String),
+    AutoDetectFailed,
+}
+
+impl LanguageResolution {
+    /// The code to filter on, if any.
+    fn as_filter(&self) -> Option<&str> {
+        match self {
+            LanguageResolution::Detected(code) => Some(code.as_str()),
+            LanguageResolution::NotRequested | LanguageResolution::AutoDetectFailed => None,
+        }
+    }
+}
+
+/// Resolves `--language` into a filter outcome: `"auto"` detects the query's
+/// own language, an explicit code is used as-is, and no flag leaves language
+/// filtering disabled.
+fn resolve_language(language: Option<&str>, query: &str) -> LanguageResolution {
+    match language {
+        Some("auto") => match detect_language(query) {
+            Some(code) => LanguageResolution::Detected(code),
+            None => LanguageResolution::AutoDetectFailed,
+        },
+        Some(code) => LanguageResolution::Detected(code.to_string()),
+        None => LanguageResolution::NotRequested,
+    }
+}
+
+fn format_page(
+    page: &SearchPage,
+    query: &str,
+    offset: usize,
+    limit: usize,
+    facets: Option<&HashMap<String, i64>>,
+    format: &OutputFormat,
+    language: &LanguageResolution,
+) -> Result<String> {
+    let results = &page.results;
+    let has_more = page.has_more;
+    let auto_detect_failed = matches!(language, LanguageResolution::AutoDetectFailed);
+
     if results.is_empty() {
         return match format {
-            OutputFormat::Json | OutputFormat::Jsonl => Ok(String::from("{\"results\":[],\"count\":0}")),
+            OutputFormat::Json => {
+                let mut output = serde_json::json!({
+                    "results": [],
+                    "count": 0,
+                    "total": page.total,
+                    "offset": offset,
+                    "limit": limit,
+                    "has_more": false
+                });
+                if let Some(facets) = facets {
+                    output["facets"] = serde_json::json!(facets);
+                }
+                if auto_detect_failed {
+                    output["language_auto_detect_failed"] = serde_json::json!(true);
+                }
+                Ok(serde_json::to_string_pretty(&output)?)
+            }
+            OutputFormat::Jsonl => Ok(String::from("{\"results\":[],\"count\":0}")),
             OutputFormat::Text => Ok(String::from("No results found.")),
         };
     }
 
     match format {
         OutputFormat::Jsonl => {
-            // Compact JSONL format for agent consumption
+            // Compact JSONL format for agent consumption. Each ranked line
+            // carries its own resumable cursor so an agent can pass the last
+            // line's "cur" back as --after to continue the scan
+            // deterministically; browse-mode lines have no rank and a null
+            // "cur".
             let lines: Vec<String> = results
                 .iter()
                 .map(|r| {
+                    let cursor = r.rank.map(|rank| encode_cursor(rank, r.rowid));
                     serde_json::json!({
                         "p": r.path,
                         "c": r.category,
                         "t": r.title,
                         "s": r.snippet,
-                        "r": r.rank
+                        "r": r.rank,
+                        "cur": cursor
                     })
                     .to_string()
                 })
@@ -212,46 +1018,476 @@ fn format_results(results: &[SearchResult], query: &str, format: &OutputFormat)
             Ok(lines.join("\n"))
         }
         OutputFormat::Json => {
-            let output = serde_json::json!({
+            let mut output = serde_json::json!({
                 "query": query,
                 "count": results.len(),
-                "results": results
+                "results": results,
+                "total": page.total,
+                "offset": offset,
+                "limit": limit,
+                "has_more": has_more
             });
+            if let Some(facets) = facets {
+                output["facets"] = serde_json::json!(facets);
+            }
+            if auto_detect_failed {
+                output["language_auto_detect_failed"] = serde_json::json!(true);
+            }
             Ok(serde_json::to_string_pretty(&output)?)
         }
         OutputFormat::Text => {
             let mut output = Vec::new();
-            output.push(format!("Found {} results for '{}':\n", results.len(), query));
-            
+            output.push(format!(
+                "Found {} of {} results for '{}':\n",
+                results.len(),
+                page.total,
+                query
+            ));
+
             for (i, r) in results.iter().enumerate() {
-                output.push(format!("{}. [{}] {}", i + 1, r.category, r.title));
+                output.push(format!("{}. [{}] {}", offset + i + 1, r.category, r.title));
                 output.push(format!("   Path: {}", r.path));
                 output.push(format!("   {}\n", r.snippet));
             }
-            
+
             Ok(output.join("\n"))
         }
     }
 }
 
-fn main() -> Result<()> {
-    let args = Args::parse();
-
-    // Check if database exists
+fn run_search(args: &SearchArgs) -> Result<()> {
     if !args.db_path.exists() {
         anyhow::bail!("Database not found: {:?}", args.db_path);
     }
 
-    let searcher = Searcher::new(&args.db_path)?;
+    let conn = Connection::open(&args.db_path)
+        .with_context(|| format!("Failed to open database: {:?}", args.db_path))?;
+    let searcher = Searcher::new(&conn);
 
-    let results = if let Some(category) = &args.category {
-        searcher.search_category(&args.query, category, args.limit, args.max_snippet)?
+    let snippet_tokens = snippet_token_count(args.max_snippet);
+    let after = args.after.as_deref().map(decode_cursor).transpose()?;
+    let language = resolve_language(args.language.as_deref(), &args.query);
+    let limit = clamp_limit(args.limit);
+    let offset = effective_offset(clamp_offset(args.offset), after);
+
+    let params = SearchParams {
+        language: language.as_filter(),
+        limit,
+        offset,
+        after,
+        snippet_tokens,
+        highlight: Highlight { pre: &args.highlight_pre, post: &args.highlight_post },
+        fuzzy: args.fuzzy,
+        typo_tolerance: args.typo_tolerance,
+    };
+
+    let page = if let Some(category) = &args.category {
+        searcher.search_category(&args.query, category, &params)?
     } else {
-        searcher.search(&args.query, args.limit, args.max_snippet)?
+        searcher.search(&args.query, &params)?
     };
 
-    let output = format_results(&results, &args.query, &args.format)?;
+    let facets = if args.facets {
+        Some(searcher.resolve_facets(&args.query)?)
+    } else {
+        None
+    };
+
+    let output = format_page(
+        &page,
+        &args.query,
+        offset,
+        limit,
+        facets.as_ref(),
+        &args.format,
+        &language,
+    )?;
     println!("{}", output);
 
     Ok(())
 }
+
+/// Shared state handed to every Axum handler.
+///
+/// The pool hands out pooled `rusqlite::Connection`s so concurrent agents
+/// don't serialize on a single handle the way the one-shot CLI does.
+struct AppState {
+    pool: Pool<SqliteConnectionManager>,
+}
+
+/// Errors from `search_handler`'s blocking closure, kept distinct from a
+/// plain `anyhow::Error` so pool exhaustion can still map to 503 rather than
+/// the 500 every other failure in that closure gets.
+enum HandlerError {
+    PoolExhausted(r2d2::Error),
+    Query(anyhow::Error),
+}
+
+impl From<anyhow::Error> for HandlerError {
+    fn from(err: anyhow::Error) -> Self {
+        HandlerError::Query(err)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchQuery {
+    query: String,
+    limit: Option<usize>,
+    max_snippet: Option<usize>,
+    category: Option<String>,
+    format: Option<OutputFormat>,
+    #[serde(default)]
+    fuzzy: bool,
+    typo_tolerance: Option<f64>,
+    highlight_pre: Option<String>,
+    highlight_post: Option<String>,
+    #[serde(default)]
+    offset: usize,
+    after: Option<String>,
+    #[serde(default)]
+    facets: bool,
+    /// A specific language code, or "auto" to detect the query's own
+    /// language. "auto" is unreliable on short queries; see the CLI's
+    /// `--language` help for details. On a failed auto-detection no filter
+    /// is applied, and a `json` response sets "language_auto_detect_failed"
+    /// (`jsonl` and `text` responses do not).
+    language: Option<String>,
+}
+
+async fn search_handler(
+    State(state): State<Arc<AppState>>,
+    AxumQuery(params): AxumQuery<SearchQuery>,
+) -> Result<String, (axum::http::StatusCode, String)> {
+    let pool = state.pool.clone();
+    let query = params.query.clone();
+    let limit = clamp_limit(params.limit.unwrap_or(10));
+    let snippet_tokens = snippet_token_count(params.max_snippet.unwrap_or(500));
+    let format = params.format.clone().unwrap_or(OutputFormat::Json);
+    let fuzzy = params.fuzzy;
+    let typo_tolerance = params.typo_tolerance.unwrap_or(0.2);
+    let highlight_pre = params.highlight_pre.clone().unwrap_or_default();
+    let highlight_post = params.highlight_post.clone().unwrap_or_default();
+    let after = params
+        .after
+        .as_deref()
+        .map(decode_cursor)
+        .transpose()
+        .map_err(|e| (axum::http::StatusCode::BAD_REQUEST, e.to_string()))?;
+    let offset = effective_offset(clamp_offset(params.offset), after);
+
+    let want_facets = params.facets;
+    let language = resolve_language(params.language.as_deref(), &params.query);
+    let language_for_response = language.clone();
+
+    let (page, facets) = tokio::task::spawn_blocking(
+        move || -> std::result::Result<(SearchPage, Option<HashMap<String, i64>>), HandlerError> {
+            // Acquiring a pooled connection can block (pool exhausted, a slow
+            // query holding one) just like the query work below, so it
+            // belongs in this blocking closure rather than the async handler
+            // body, where it would stall a tokio worker thread.
+            let conn = pool.get().map_err(HandlerError::PoolExhausted)?;
+            let searcher = Searcher::new(&conn);
+            let search_params = SearchParams {
+                language: language.as_filter(),
+                limit,
+                offset,
+                after,
+                snippet_tokens,
+                highlight: Highlight { pre: &highlight_pre, post: &highlight_post },
+                fuzzy,
+                typo_tolerance,
+            };
+            let page = if let Some(category) = &params.category {
+                searcher.search_category(&params.query, category, &search_params)
+            } else {
+                searcher.search(&params.query, &search_params)
+            }?;
+            let facets = if want_facets {
+                Some(searcher.resolve_facets(&params.query)?)
+            } else {
+                None
+            };
+            Ok((page, facets))
+        },
+    )
+    .await
+    .map_err(|e| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .map_err(|e| match e {
+        HandlerError::PoolExhausted(e) => (
+            axum::http::StatusCode::SERVICE_UNAVAILABLE,
+            format!("Failed to acquire pooled connection: {e}"),
+        ),
+        HandlerError::Query(e) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    })?;
+
+    format_page(
+        &page,
+        &query,
+        offset,
+        limit,
+        facets.as_ref(),
+        &format,
+        &language_for_response,
+    )
+    .map_err(|e| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+async fn run_serve(args: &ServeArgs) -> Result<()> {
+    if !args.db_path.exists() {
+        anyhow::bail!("Database not found: {:?}", args.db_path);
+    }
+
+    let manager = SqliteConnectionManager::file(&args.db_path);
+    let pool = Pool::builder()
+        .max_size(args.pool_size)
+        .build(manager)
+        .context("Failed to build SQLite connection pool")?;
+
+    let state = Arc::new(AppState { pool });
+
+    let app = Router::new()
+        .route("/api/search", get(search_handler))
+        .with_state(state);
+
+    let listener = tokio::net::TcpListener::bind(&args.bind)
+        .await
+        .with_context(|| format!("Failed to bind to {}", args.bind))?;
+
+    println!("Listening on {}", args.bind);
+    axum::serve(listener, app).await.context("HTTP server failed")?;
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+
+    match &args.command {
+        Command::Search(search_args) => run_search(search_args),
+        Command::Serve(serve_args) => run_serve(serve_args).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type DocRow<'a> = (i64, &'a str, &'a str, &'a str, &'a str, Option<&'a str>);
+
+    /// Builds an in-memory `docs`/`docs_fts` pair matching the column layout
+    /// `run_match_query`'s `snippet()`/`highlight()` calls assume (`docs_fts`
+    /// column 3 = content), populated from `(id, path, category, title,
+    /// content, lang)` tuples.
+    fn setup_db(docs: &[DocRow]) -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            r#"
+            CREATE TABLE docs (
+                id INTEGER PRIMARY KEY,
+                path TEXT NOT NULL,
+                category TEXT NOT NULL,
+                title TEXT NOT NULL,
+                content TEXT NOT NULL,
+                lang TEXT
+            );
+            CREATE VIRTUAL TABLE docs_fts USING fts5(path, category, title, content);
+            "#,
+        )
+        .unwrap();
+        for (id, path, category, title, content, lang) in docs {
+            conn.execute(
+                "INSERT INTO docs (id, path, category, title, content, lang) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                rusqlite::params![id, path, category, title, content, lang],
+            )
+            .unwrap();
+            conn.execute(
+                "INSERT INTO docs_fts (rowid, path, category, title, content) VALUES (?1, ?2, ?3, ?4, ?5)",
+                rusqlite::params![id, path, category, title, content],
+            )
+            .unwrap();
+        }
+        conn
+    }
+
+    fn widget_docs(count: i64) -> Vec<(i64, String, String, String, String, Option<String>)> {
+        (1..=count)
+            .map(|id| {
+                (
+                    id,
+                    format!("widget-{id}.md"),
+                    String::from("docs"),
+                    format!("Widget {id}"),
+                    String::from("widget widget widget"),
+                    None,
+                )
+            })
+            .collect()
+    }
+
+    fn default_params(limit: usize, offset: usize, after: Option<Cursor>) -> SearchParams<'static> {
+        SearchParams {
+            language: None,
+            limit,
+            offset,
+            after,
+            snippet_tokens: 10,
+            highlight: Highlight { pre: "", post: "" },
+            fuzzy: false,
+            typo_tolerance: 0.2,
+        }
+    }
+
+    #[test]
+    fn cursor_paging_has_more_reflects_remaining_rows() {
+        let owned = widget_docs(15);
+        let borrowed: Vec<_> = owned
+            .iter()
+            .map(|(id, path, category, title, content, lang)| {
+                (*id, path.as_str(), category.as_str(), title.as_str(), content.as_str(), lang.as_deref())
+            })
+            .collect();
+        let conn = setup_db(&borrowed);
+        let searcher = Searcher::new(&conn);
+
+        let first_page = searcher
+            .search("widget", &default_params(10, 0, None))
+            .unwrap();
+        assert_eq!(first_page.results.len(), 10);
+        assert!(first_page.has_more, "15 total, 10 returned: more should remain");
+
+        let last = first_page.results.last().unwrap();
+        let cursor = decode_cursor(&encode_cursor(last.rank.unwrap(), last.rowid)).unwrap();
+
+        let second_page = searcher
+            .search("widget", &default_params(10, 0, Some(cursor)))
+            .unwrap();
+        assert_eq!(second_page.results.len(), 5, "remaining 5 of 15 rows");
+        assert!(
+            !second_page.has_more,
+            "cursor has consumed all 15 rows, has_more must be false"
+        );
+    }
+
+    #[test]
+    fn after_cursor_takes_precedence_over_offset() {
+        let owned = widget_docs(15);
+        let borrowed: Vec<_> = owned
+            .iter()
+            .map(|(id, path, category, title, content, lang)| {
+                (*id, path.as_str(), category.as_str(), title.as_str(), content.as_str(), lang.as_deref())
+            })
+            .collect();
+        let conn = setup_db(&borrowed);
+        let searcher = Searcher::new(&conn);
+
+        let first_page = searcher
+            .search("widget", &default_params(10, 0, None))
+            .unwrap();
+        let last = first_page.results.last().unwrap();
+        let cursor = decode_cursor(&encode_cursor(last.rank.unwrap(), last.rowid)).unwrap();
+
+        let without_offset = searcher
+            .search("widget", &default_params(10, 0, Some(cursor)))
+            .unwrap();
+        let with_bogus_offset = searcher
+            .search("widget", &default_params(10, 100, Some(cursor)))
+            .unwrap();
+
+        let without_paths: Vec<_> = without_offset.results.iter().map(|r| &r.path).collect();
+        let with_paths: Vec<_> = with_bogus_offset.results.iter().map(|r| &r.path).collect();
+        assert_eq!(
+            without_paths, with_paths,
+            "--offset must not skip rows past an --after cursor"
+        );
+    }
+
+    #[test]
+    fn facet_counts_aggregate_by_category() {
+        let docs = [
+            (1, "a.md", "guide", "Widget setup", "widget install steps", None),
+            (2, "b.md", "guide", "Widget teardown", "widget removal steps", None),
+            (3, "c.md", "reference", "Widget API", "widget api reference", None),
+        ];
+        let conn = setup_db(&docs);
+        let searcher = Searcher::new(&conn);
+
+        let facets = searcher.resolve_facets("widget").unwrap();
+        assert_eq!(facets.get("guide"), Some(&2));
+        assert_eq!(facets.get("reference"), Some(&1));
+    }
+
+    #[test]
+    fn facets_in_browse_mode_do_not_crash() {
+        let docs = [
+            (1, "a.md", "guide", "Widget setup", "widget install steps", None),
+            (2, "b.md", "reference", "Widget API", "widget api reference", None),
+        ];
+        let conn = setup_db(&docs);
+        let searcher = Searcher::new(&conn);
+
+        // An empty query used to be passed straight to `docs_fts MATCH ''`,
+        // which FTS5 rejects with a hard syntax error.
+        let facets = searcher.resolve_facets("").unwrap();
+        assert_eq!(facets.get("guide"), Some(&1));
+        assert_eq!(facets.get("reference"), Some(&1));
+    }
+
+    #[test]
+    fn fuzzy_fallback_catches_a_non_prefix_typo() {
+        let docs = [(
+            1,
+            "parallel.md",
+            "guide",
+            "Parallel computing",
+            "an introduction to parallel computing techniques",
+            None,
+        )];
+        let conn = setup_db(&docs);
+        let searcher = Searcher::new(&conn);
+
+        // "paralell" is a transposition, not a prefix, of "parallel" -- the
+        // prefix-only FTS5 candidate query used to never surface it.
+        let mut params = default_params(10, 0, None);
+        params.fuzzy = true;
+        let page = searcher.search("paralell", &params).unwrap();
+        assert_eq!(page.results.len(), 1);
+        assert_eq!(page.results[0].path, "parallel.md");
+    }
+
+    #[test]
+    fn iso639_1_mapping_matches_detect_language() {
+        assert_eq!(iso639_1(whatlang::Lang::Fra), "fr");
+        assert_eq!(iso639_1(whatlang::Lang::Eng), "en");
+    }
+
+    #[test]
+    fn auto_detect_resolves_to_two_letter_code_matching_docs_lang() {
+        let docs = [(
+            1,
+            "fr.md",
+            "guide",
+            "Cuisine francaise",
+            "Le chat mange la nourriture rapidement dans la cuisine",
+            Some("fr"),
+        )];
+        let conn = setup_db(&docs);
+        let searcher = Searcher::new(&conn);
+
+        // Auto-detection used to return whatlang's "fra" (ISO 639-3), which
+        // never matches a corpus using the conventional two-letter "fr" in
+        // docs.lang -- an explicit `--language fr` filter would work while
+        // `--language auto` on the same content silently found nothing.
+        let language = resolve_language(
+            Some("auto"),
+            "Le chat mange la nourriture rapidement dans la cuisine",
+        );
+        let filter = language.as_filter();
+        assert_eq!(filter, Some("fr"));
+
+        let mut params = default_params(10, 0, None);
+        params.language = filter;
+        let page = searcher.search("cuisine", &params).unwrap();
+        assert_eq!(page.results.len(), 1);
+    }
+}